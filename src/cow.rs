@@ -1,6 +1,6 @@
 // Copyright 2016 Jeffrey Burdges
 
-// //! Zeroing drop wrapper types.
+//! Zeroing drop wrapper types.
 
 use std::boxed::Box;
 use std::ops::{Deref,DerefMut};
@@ -12,7 +12,7 @@ use super::*;
 /// Zeroing drop copy-on-write type for `Copy` types.
 ///
 /// Assuming `T: Copy`, a `ZeroDrop<T>` wraps a `Box<T>`
-/// and zeros it when dropped.  Unlike `Cow`, we must use 
+/// and zeros it when dropped.  Unlike `Cow`, we must use
 /// `Box` because LLVM moves data wildly around the stack.
 pub enum ZeroDropCow<'a, T: Copy + 'a> {
     /// Borrowed data.
@@ -60,8 +60,28 @@ impl<'a, T> ZeroDropCow<'a, T> where T: 'a+Copy {
         ZeroDropCow::Boxed(b)
     }
 
-    /// Convert a `ZeroDrowCow` into a `ZeroDrop`, copying if still borrowed.
-    pub fn into_boxed(mut self) -> ZeroDrop<T> {
+    /// Promote a `Borrowed` to a freshly allocated, zeroing `Boxed`
+    /// on first mutation, mirroring `std::borrow::Cow::to_mut`.
+    ///
+    /// The `Borrowed` variant is never mutated or zeroed in place: we
+    /// copy it into a new box, so the caller's original value is left
+    /// untouched.
+    pub fn to_mut(&mut self) -> &mut T {
+        match *self {
+            ZeroDropCow::Borrowed(borrowed) => {
+                *self = ZeroDropCow::new_copy(borrowed);
+                match *self {
+                    ZeroDropCow::Borrowed(..) => unreachable!(),
+                    ZeroDropCow::Boxed(ref mut owned) => owned.deref_mut(),
+                }
+            }
+            ZeroDropCow::Boxed(ref mut owned) => owned.deref_mut(),
+        }
+    }
+
+    /// Convert a `ZeroDropCow` into a `ZeroDrop`, copying if still
+    /// borrowed, mirroring `std::borrow::Cow::into_owned`.
+    pub fn into_owned(mut self) -> ZeroDrop<T> {
         match self {
             ZeroDropCow::Borrowed(b) => ZeroDrop::new_copy(b),
             ZeroDropCow::Boxed(ref mut o) => {
@@ -114,7 +134,7 @@ impl<'a, T,U> AsRef<U> for ZeroDropCow<'a, T> where T: 'a+Copy+AsRef<U> {
         use self::ZeroDropCow::*;
         match *self {
             Borrowed(b) => b.as_ref(),
-            Boxed(ref o) => o.as_ref().as_ref(), 
+            Boxed(ref o) => o.as_ref().as_ref(),
         }
     }
 }
@@ -125,35 +145,12 @@ impl<'a, T> Borrow<T> for ZeroDropCow<'a, T> where T: 'a+Copy {
         use self::ZeroDropCow::*;
         match *self {
             Borrowed(b) => b,
-            Boxed(ref o) => o.borrow(), 
+            Boxed(ref o) => o.borrow(),
         }
     }
 }
 // I donno if any more `Borrow<_>`s make sense here.
 
-/*
-trait ConstantTimeEq {
-    fn constant_time_eq(a: &Self, b: &Self) -> bool;
-}
-
-impl<T> ConstantTimeEq for [T] where T: ConstantTimeEq {
-    fn constant_time_eq(x: &Self, y: &Self) -> bool {
-        ;
-    }
-}
-
-/// We implement `PartialEq` to be a constant time comparison, so that
-/// noone may define it otherwise.  
-impl<T> PartialEq for ZeroDrop<T> where T: ConstantTimeEq {
-    fn eq(&self, other: &ZeroDrop<T>) -> bool {
-        ::consistenttime::ct_u8_slice_eq(&self.0, &other.0)
-    }
-}
-impl<T> Eq for Secret<T>  where T: Copy { }
-*/
-
-
-/*
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,20 +158,64 @@ mod tests {
     #[test]
     fn zeroing_drops() {
         let p : *const [u8; 32];
-        let s = ZeroDropCow::new_insecure([3u8; 32]);  
+        let s = ZeroDropCow::new_insecure([3u8; 32]);
         p = s.deref();
         ::std::mem::drop(s);
         unsafe { assert_eq!(*p,[0u8; 32]); }
     }
+
     #[test]
     #[should_panic(expected = "assertion failed")]
     fn not_droped() {
         let p : *const [u8; 32];
-        let s = ZeroDropCow::new_insecure([3u8; 32]);  
+        let s = ZeroDropCow::new_insecure([3u8; 32]);
         p = s.deref();
         // ::std::mem::drop(s);
         unsafe { assert_eq!(*p,[0u8; 32]); }
     }
-}
-*/
 
+    #[test]
+    fn borrowed_is_not_zeroed_on_drop() {
+        let original = [3u8; 32];
+        let s = ZeroDropCow::new(&original);
+        ::std::mem::drop(s);
+        assert_eq!(original, [3u8; 32]);
+    }
+
+    #[test]
+    fn to_mut_promotes_borrowed_without_touching_original() {
+        let original = [3u8; 32];
+        let mut s = ZeroDropCow::new(&original);
+        s.to_mut()[0] = 9;
+        assert_eq!(original, [3u8; 32]); // untouched
+        assert_eq!(s.deref()[0], 9);
+    }
+
+    #[test]
+    fn to_mut_zeroes_the_promoted_copy_on_drop() {
+        let original = [3u8; 32];
+        let mut s = ZeroDropCow::new(&original);
+        s.to_mut();
+        let p = s.deref() as *const [u8; 32];
+        ::std::mem::drop(s);
+        unsafe { assert_eq!(*p,[0u8; 32]); }
+        assert_eq!(original, [3u8; 32]); // still untouched
+    }
+
+    #[test]
+    fn to_mut_on_already_boxed_does_not_reallocate() {
+        let mut s = ZeroDropCow::new_insecure([3u8; 32]);
+        let first_ptr = s.deref() as *const [u8; 32];
+        s.to_mut()[0] = 9;
+        assert_eq!(s.deref() as *const [u8; 32], first_ptr);
+    }
+
+    #[test]
+    fn into_owned_copies_a_borrow() {
+        let original = [3u8; 32];
+        let s = ZeroDropCow::new(&original);
+        let owned = s.into_owned();
+        assert_eq!(*owned, [3u8; 32]);
+        assert_eq!(original, [3u8; 32]);
+    }
+}
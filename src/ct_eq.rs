@@ -0,0 +1,67 @@
+// Copyright 2016 Jeffrey Burdges
+
+//! Constant-time equality for secrets.
+
+/// Constant-time equality comparison.
+///
+/// Implementors must ensure the number of byte operations performed by
+/// `ct_eq` depends only on the declared length of `Self`, never on the
+/// data being compared, so that equality checks on secrets cannot leak
+/// timing information.  We do not hide the *length* itself though:
+/// comparing values of unequal length simply returns `false` rather
+/// than paying for a full comparison against the shorter one.
+pub trait ConstantTimeEq {
+    /// Compare `self` and `other` in constant time, returning `true`
+    /// iff every byte matches.
+    fn ct_eq(&self, other: &Self) -> bool;
+}
+
+impl ConstantTimeEq for [u8] {
+    fn ct_eq(&self, other: &Self) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        // `consistenttime::ct_u8_slice_eq` already accumulates the
+        // difference across every byte without branching or early
+        // exit, so we simply route the comparison through it rather
+        // than reimplement the accumulator ourselves.
+        ::consistenttime::ct_u8_slice_eq(self, other)
+    }
+}
+
+macro_rules! ct_eq_array_impl {
+    ($($N:expr)+) => {
+        $(
+            impl ConstantTimeEq for [u8; $N] {
+                fn ct_eq(&self, other: &Self) -> bool {
+                    (&self[..]).ct_eq(&other[..])
+                }
+            }
+        )+
+    }
+}
+
+// Common secret lengths: AES/ChaCha keys and blocks, hash digests, etc.
+ct_eq_array_impl! {
+    1 2 4 8 16 20 24 28 32 48 56 64 128 256
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_arrays_match() {
+        assert!([1u8,2,3,4].ct_eq(&[1u8,2,3,4]));
+    }
+
+    #[test]
+    fn differing_arrays_mismatch() {
+        assert!(! [1u8,2,3,4].ct_eq(&[1u8,2,3,5]));
+    }
+
+    #[test]
+    fn differing_lengths_mismatch() {
+        assert!(! (&[1u8,2,3][..]).ct_eq(&[1u8,2,3,4][..]));
+    }
+}
@@ -5,12 +5,20 @@
 #![feature(core_intrinsics)]
 
 extern crate consistenttime;
+#[cfg(feature = "libc")]
+extern crate libc;
 
+mod ct_eq;
 mod zd;
 mod zdd;
-// mod cow;
+mod zdarc;
+mod zguard;
+mod cow;
 
+pub use ct_eq::ConstantTimeEq;
 pub use zd::ZeroDrop;
 pub use zdd::ZeroDropDrop;
-// pub use cow::ZeroDropCow;
+pub use zdarc::ZeroDropArc;
+pub use zguard::ZeroGuard;
+pub use cow::ZeroDropCow;
 
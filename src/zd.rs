@@ -7,22 +7,34 @@ use std::ops::{Deref,DerefMut};
 use std::convert::{AsRef,AsMut};
 use std::borrow::{Borrow,BorrowMut};
 
+use super::ConstantTimeEq;
+
+/// Whether a `ZeroDrop<T>` holds an `mlock`ed allocation.
+///
+/// Without the `libc` feature there is nothing to track, so this
+/// collapses to a zero-sized `()` and the locking code below never
+/// gets compiled in.
+#[cfg(feature = "libc")]
+type LockFlag = bool;
+#[cfg(not(feature = "libc"))]
+type LockFlag = ();
+
 /// Zeroing drop wrapper type for `Copy` type.
 ///
 /// Assuming `T: Copy`, a `ZeroDrop<T>` wraps a `Box<T>`
-/// and zeros it when dropped.  We must use `Box` because 
+/// and zeros it when dropped.  We must use `Box` because
 /// LLVM moves data on the stack willy nilly.
 ///
 /// ```rust
 /// let p : *const [u8; 32];
-/// let s = zerodrop::ZeroDrop::new_clone(&[3u8; 32]);  
+/// let s = zerodrop::ZeroDrop::new_copy(&[3u8; 32]);
 /// p = &*s;
 /// std::mem::drop(s);
 /// unsafe { assert_eq!(*p,[0u8; 32]); }
 /// ```
 ///
 /// We recommend abstracting usage of `ZeroDrop` as follows because
-/// `ZeroDrop` does not `mlock` data.
+/// `ZeroDrop` does not `mlock` data by default.
 /// ```rust,ignore
 /// type Secret<T> = ZeroDrop<T> where T: Copy+Default;
 /// ```
@@ -32,43 +44,60 @@ use std::borrow::{Borrow,BorrowMut};
 /// ```rust,ignore
 /// struct MySecret(pub ZeroDrop<[u8; 32]>);
 /// ```
+///
+/// With the `libc` feature enabled, `new_locked`/`new_copy_locked`
+/// additionally `mlock` the allocation so the kernel never swaps the
+/// secret to disk, and `MADV_DONTDUMP` it on Linux so it is excluded
+/// from core dumps.
+///
+/// `T` need not be `Sized`: `ZeroDrop<[u8]>` wraps a runtime-length
+/// secret, e.g. a protocol-negotiated key or a password, allocated via
+/// `from_slice`/`zeroed_slice` below.  Most constructors still require
+/// `T: Copy+Sized` since they move a `T` into the box; the unsized
+/// slice constructors are the exception.
 #[derive(Debug)]
-pub struct ZeroDrop<T>(Box<T>) where T: Copy;
+pub struct ZeroDrop<T: ?Sized>(Box<T>, LockFlag);
 
-/// Zero a `ZeroDrop<T>` when dropped.
-impl<T> Drop for ZeroDrop<T> where T: Copy {
+/// Zero a `ZeroDrop<T>` when dropped, `T: ?Sized` included.
+///
+/// We compute the byte count at runtime via `size_of_val` rather than
+/// assuming a single `T`-sized element, so this covers both a boxed
+/// `T: Copy` and a boxed `[u8]` of whatever length it was allocated
+/// with.
+impl<T: ?Sized> Drop for ZeroDrop<T> {
     #[inline(never)]
     fn drop(&mut self) {
-        let s: &mut T = self.0.deref_mut();
-        unsafe { ::std::intrinsics::volatile_set_memory::<T>(s,0,1) }
-        // We could do this if we had default
-        // *self.0 = Default::default();
+        let len = ::std::mem::size_of_val::<T>(self.0.deref());
+        let s = self.0.deref_mut() as *mut T as *mut u8;
+        unsafe { ::std::intrinsics::volatile_set_memory::<u8>(s,0,len) }
+        #[cfg(feature = "libc")]
+        self.munlock_if_locked();
     }
 }
 
 /// Create a `ZeroDrop<T>` for a `T: Copy` consisting of a `Box<T>`
-/// that will be zeroed when dropped. 
+/// that will be zeroed when dropped.
 impl<T> ZeroDrop<T> where T: Copy {
     /// Insecure as `t` likely gets placed on the stack
     pub fn new_insecure(t: T) -> ZeroDrop<T> {
-        ZeroDrop(Box::new(t))
+        ZeroDrop(Box::new(t), Default::default())
     }
 
     /// Use provided `Box<T>`
     pub fn new_box(b: Box<T>) -> ZeroDrop<T> {
-        ZeroDrop(b)
+        ZeroDrop(b, Default::default())
     }
 
     /// Secure but unsafe
     pub unsafe fn new_uninitialized() -> ZeroDrop<T> {
-        ZeroDrop(Box::new(::std::mem::uninitialized::<T>()))
+        ZeroDrop(Box::new(::std::mem::uninitialized::<T>()), Default::default())
     }
 
     /// Allocate box and copy data into it from reference
     pub fn new_copy(t: &T) -> ZeroDrop<T> {
         let mut b = Box::new(unsafe { ::std::mem::uninitialized::<T>() });
         unsafe { ::std::ptr::copy_nonoverlapping::<T>(t,b.deref_mut(),1) }
-        ZeroDrop(b)
+        ZeroDrop(b, Default::default())
     }
 
     pub unsafe fn zero_out(&mut self) {
@@ -81,28 +110,160 @@ impl<T> ZeroDrop<T> where T: Copy {
         // unsafe { z.zero_out() }
         let mut b = Box::new(unsafe { ::std::mem::uninitialized::<T>() });
         unsafe { ::std::intrinsics::volatile_set_memory::<T>(b.deref_mut(),0,1) }
-        ZeroDrop(b)
+        ZeroDrop(b, Default::default())
+    }
+}
+
+/// Constructors for runtime-length secrets, e.g. protocol-negotiated
+/// keys or passwords, that cannot be sized as a fixed array.
+impl ZeroDrop<[u8]> {
+    /// Allocate a `ZeroDrop<[u8]>` of length `s.len()` and copy `s`
+    /// into it.
+    pub fn from_slice(s: &[u8]) -> ZeroDrop<[u8]> {
+        let mut z = ZeroDrop::zeroed_slice(s.len());
+        z.deref_mut().copy_from_slice(s);
+        z
+    }
+
+    /// Allocate a zeroed `ZeroDrop<[u8]>` of the given length.
+    pub fn zeroed_slice(len: usize) -> ZeroDrop<[u8]> {
+        let b: Box<[u8]> = vec![0u8; len].into_boxed_slice();
+        ZeroDrop(b, Default::default())
+    }
+}
+
+/// FFI handoff that preserves the zeroing-on-drop guarantee across
+/// the boundary.
+///
+/// Crypto code frequently must pass a secret buffer to a C library.
+/// `into_foreign` leaks the box as a raw pointer so C can read or
+/// write it in place, and `from_foreign` reconstructs the `ZeroDrop<T>`
+/// so the eventual Rust-side drop still zeroes and frees it.
+///
+/// # Safety contract
+/// Exactly one `from_foreign` call must be made per `into_foreign`
+/// call, and the foreign side must not free the pointer itself —
+/// otherwise the zeroing-on-drop guarantee is lost.
+impl<T: ?Sized> ZeroDrop<T> {
+    /// Leak the box as a raw pointer without zeroing it, handing
+    /// ownership to foreign code until `from_foreign` reclaims it.
+    pub fn into_foreign(self) -> *mut T {
+        let this = ::std::mem::ManuallyDrop::new(self);
+        let b: Box<T> = unsafe { ::std::ptr::read(&this.0) };
+        Box::into_raw(b)
+    }
+
+    /// Reconstruct a `ZeroDrop<T>` from a pointer previously returned
+    /// by `into_foreign`, so that dropping it zeroes and frees the
+    /// memory as usual.
+    ///
+    /// # Safety
+    /// `ptr` must come from a matching `into_foreign` call that has
+    /// not already been reclaimed, and must not have been freed by the
+    /// foreign side.  If the original `ZeroDrop<T>` was `mlock`ed, that
+    /// lock state is not preserved across the boundary: `drop` on the
+    /// reconstructed value will not `munlock`.
+    pub unsafe fn from_foreign(ptr: *mut T) -> ZeroDrop<T> {
+        ZeroDrop(Box::from_raw(ptr), Default::default())
+    }
+
+    /// Peek at a value still checked out to foreign code without
+    /// claiming ownership of it, so a caller does not have to call
+    /// `from_foreign` (and thus take over zeroing responsibility) just
+    /// to read it.
+    ///
+    /// # Safety
+    /// `ptr` must come from an `into_foreign` call that has not yet
+    /// been reclaimed by `from_foreign`, and the returned reference
+    /// must not outlive that window.
+    pub unsafe fn foreign_borrow<'a>(ptr: *mut T) -> &'a T {
+        &*ptr
+    }
+}
+
+/// `mlock`-backed constructors, available with the `libc` feature.
+#[cfg(feature = "libc")]
+impl<T> ZeroDrop<T> where T: Copy {
+    /// Allocate a box, copy `t` into it, and `mlock` the allocation so
+    /// the kernel will never swap it to disk.
+    ///
+    /// Fails if `mlock` fails, e.g. because `RLIMIT_MEMLOCK` is
+    /// exhausted.
+    pub fn new_copy_locked(t: &T) -> ::std::io::Result<ZeroDrop<T>> {
+        let mut z = ZeroDrop::new_copy(t);
+        z.mlock()?;
+        Ok(z)
+    }
+
+    /// As `new_copy_locked` but takes `t` by value.
+    ///
+    /// Insecure in that `t` likely gets placed on the stack before
+    /// being moved into the locked allocation.
+    pub fn new_locked(t: T) -> ::std::io::Result<ZeroDrop<T>> {
+        let mut z = ZeroDrop::new_insecure(t);
+        z.mlock()?;
+        Ok(z)
+    }
+
+    /// `mlock` the allocation in place, recording that `drop` must
+    /// `munlock` it again.  On Linux also marks the pages
+    /// `MADV_DONTDUMP` so the secret is excluded from core dumps.
+    fn mlock(&mut self) -> ::std::io::Result<()> {
+        let ptr = self.0.deref_mut() as *mut T as *mut ::libc::c_void;
+        let len = ::std::mem::size_of::<T>();
+        if unsafe { ::libc::mlock(ptr, len) } != 0 {
+            return Err(::std::io::Error::last_os_error());
+        }
+        // Unlike `mlock`, `madvise` rejects a start address that is not
+        // page-aligned, so widen the range to the enclosing pages.
+        #[cfg(target_os = "linux")]
+        unsafe {
+            let page = ::libc::sysconf(::libc::_SC_PAGESIZE) as usize;
+            let start = (ptr as usize) & !(page - 1);
+            let end = ptr as usize + len;
+            ::libc::madvise(start as *mut ::libc::c_void, end - start, ::libc::MADV_DONTDUMP);
+        }
+        self.1 = true;
+        Ok(())
+    }
+}
+
+/// `munlock` is independent of `Copy`, so it lives in its own
+/// `?Sized` impl block shared by every `ZeroDrop<T>`, including
+/// `ZeroDrop<[u8]>`.
+#[cfg(feature = "libc")]
+impl<T: ?Sized> ZeroDrop<T> {
+    fn munlock_if_locked(&mut self) {
+        if self.1 {
+            let len = ::std::mem::size_of_val::<T>(self.0.deref());
+            let ptr = self.0.deref_mut() as *mut T as *mut u8 as *mut ::libc::c_void;
+            unsafe { ::libc::munlock(ptr, len); }
+            self.1 = false;
+        }
     }
 }
 
 impl<T> Default for ZeroDrop<T> where T: Copy+Default {
     fn default() -> ZeroDrop<T> {
-        ZeroDrop(Default::default())
+        ZeroDrop(Default::default(), Default::default())
     }
 }
 
 /// `Clone` the underlying `Box`
+///
+/// The clone is never locked, even if `self` is: call `new_copy_locked`
+/// explicitly if the clone must also be `mlock`ed.
 impl<T> Clone for ZeroDrop<T> where T: Copy {
     fn clone(&self) -> ZeroDrop<T> {
-        ZeroDrop(self.0.clone())
+        ZeroDrop(self.0.clone(), Default::default())
     }
     fn clone_from(&mut self, source: &ZeroDrop<T>) {
         self.0.clone_from(&source.0);
     }
 }
 
-/// Delegate `Deref` to `Box`
-impl<T> Deref for ZeroDrop<T> where T: Copy {
+/// Delegate `Deref` to `Box`, `T: ?Sized` included.
+impl<T: ?Sized> Deref for ZeroDrop<T> {
     type Target = T;
 
     fn deref(&self) -> &T {
@@ -110,37 +271,37 @@ impl<T> Deref for ZeroDrop<T> where T: Copy {
     }
 }
 
-/// Delegate `DerefMut` to `Box`
-impl<T> DerefMut for ZeroDrop<T> where T: Copy {
+/// Delegate `DerefMut` to `Box`, `T: ?Sized` included.
+impl<T: ?Sized> DerefMut for ZeroDrop<T> {
     fn deref_mut(&mut self) -> &mut T {
         self.0.deref_mut()
     }
 }
 
-/// Delegate `AsRef<_>` to `Box`
-impl<T,U> AsRef<U> for ZeroDrop<T> where T: Copy+AsRef<U> {
+/// Delegate `AsRef<_>` to `Box`, `T: ?Sized` included.
+impl<T: ?Sized,U> AsRef<U> for ZeroDrop<T> where T: AsRef<U> {
     fn as_ref(&self) -> &U {
         self.0.as_ref().as_ref()
     }
 }
 
-/// Delegate `AsMut<_>` to `Box`
-impl<T,U> AsMut<U> for ZeroDrop<T> where T: Copy+AsMut<U> {
+/// Delegate `AsMut<_>` to `Box`, `T: ?Sized` included.
+impl<T: ?Sized,U> AsMut<U> for ZeroDrop<T> where T: AsMut<U> {
     fn as_mut(&mut self) -> &mut U {
         self.0.as_mut().as_mut()
     }
 }
 
-/// Delegate `Borrow<_>` to `Box`
-impl<T> Borrow<T> for ZeroDrop<T> where T: Copy {
+/// Delegate `Borrow<_>` to `Box`, `T: ?Sized` included.
+impl<T: ?Sized> Borrow<T> for ZeroDrop<T> {
     fn borrow(&self) -> &T {
         self.0.borrow()
     }
 }
 // I donno if any more `Borrow<_>` make sense here.
 
-/// Delegate `BorrowMut<_>` to `Box`
-impl<T> BorrowMut<T> for ZeroDrop<T> where T: Copy {
+/// Delegate `BorrowMut<_>` to `Box`, `T: ?Sized` included.
+impl<T: ?Sized> BorrowMut<T> for ZeroDrop<T> {
     fn borrow_mut(&mut self) -> &mut T {
         self.0.borrow_mut()
     }
@@ -149,26 +310,14 @@ impl<T> BorrowMut<T> for ZeroDrop<T> where T: Copy {
 
 
 
-/*
-trait ConstantTimeEq {
-    fn constant_time_eq(a: &Self, b: &Self) -> bool;
-}
-
-impl<T> ConstantTimeEq for [T] where T: ConstantTimeEq {
-    fn constant_time_eq(x: &Self, y: &Self) -> bool {
-        ;
-    }
-}
-
 /// We implement `PartialEq` to be a constant time comparison, so that
-/// noone may define it otherwise.  
-impl<T> PartialEq for ZeroDrop<T> where T: ConstantTimeEq {
+/// noone may define it otherwise.
+impl<T> PartialEq for ZeroDrop<T> where T: Copy+ConstantTimeEq {
     fn eq(&self, other: &ZeroDrop<T>) -> bool {
-        ::consistenttime::ct_u8_slice_eq(&self.0, &other.0)
+        self.0.deref().ct_eq(other.0.deref())
     }
 }
-impl<T> Eq for Secret<T>  where T: Copy { }
-*/
+impl<T> Eq for ZeroDrop<T> where T: Copy+ConstantTimeEq { }
 
 
 
@@ -188,11 +337,75 @@ mod tests {
     #[should_panic(expected = "assertion failed")]
     fn not_droped() {
         let p : *const [u8; 32];
-        let s = ZeroDrop::new_insecure([3u8; 32]);  
+        let s = ZeroDrop::new_insecure([3u8; 32]);
         p = s.deref();
         // ::std::mem::drop(s);
         unsafe { assert_eq!(*p,[0u8; 32]); }
     }
+
+    #[test]
+    fn equal_values_are_equal() {
+        assert_eq!(ZeroDrop::new_insecure([3u8; 32]), ZeroDrop::new_insecure([3u8; 32]));
+    }
+
+    #[test]
+    fn differing_values_are_not_equal() {
+        assert!(ZeroDrop::new_insecure([3u8; 32]) != ZeroDrop::new_insecure([4u8; 32]));
+    }
+
+    #[cfg(feature = "libc")]
+    #[test]
+    fn locked_roundtrips_value() {
+        let s = ZeroDrop::new_copy_locked(&[3u8; 32]).expect("mlock should succeed");
+        assert_eq!(*s.deref(), [3u8; 32]);
+    }
+
+    #[cfg(feature = "libc")]
+    #[test]
+    fn locked_values_lock_and_drop_repeatedly() {
+        // This does not observe whether `drop` actually `munlock`s: it
+        // only checks that locking succeeds and that dropping a locked
+        // `ZeroDrop` (zero, then `munlock`) does not panic, several
+        // times in a row.
+        for _ in 0..4 {
+            let s = ZeroDrop::new_locked([3u8; 32]).expect("mlock should succeed");
+            ::std::mem::drop(s);
+        }
+    }
+
+    #[test]
+    fn slice_from_slice_copies() {
+        let s = ZeroDrop::from_slice(&[1u8,2,3,4,5]);
+        assert_eq!(s.deref(), &[1u8,2,3,4,5][..]);
+    }
+
+    #[test]
+    fn slice_zeroing_drops() {
+        let p : *const u8;
+        let s = ZeroDrop::from_slice(&[7u8; 48]);
+        p = s.deref().as_ptr();
+        ::std::mem::drop(s);
+        unsafe { assert_eq!(::std::slice::from_raw_parts(p,48), &[0u8; 48][..]); }
+    }
+
+    #[test]
+    fn foreign_roundtrip_preserves_value() {
+        let s = ZeroDrop::new_insecure([3u8; 32]);
+        let ptr = s.into_foreign();
+        unsafe { (*ptr)[0] = 9 };
+        let s = unsafe { ZeroDrop::from_foreign(ptr) };
+        let mut expected = [3u8; 32];
+        expected[0] = 9;
+        assert_eq!(*s.deref(), expected);
+    }
+
+    #[test]
+    fn foreign_borrow_reads_without_reclaiming() {
+        let s = ZeroDrop::new_insecure([3u8; 32]);
+        let ptr = s.into_foreign();
+        assert_eq!(unsafe { *ZeroDrop::foreign_borrow(ptr) }, [3u8; 32]);
+        unsafe { ZeroDrop::from_foreign(ptr) };
+    }
 }
 
 
@@ -0,0 +1,162 @@
+// Copyright 2016 Jeffrey Burdges
+
+//! Reference-counted zeroing wrapper type.
+
+use std::boxed::Box;
+use std::ops::Deref;
+use std::convert::AsRef;
+use std::borrow::Borrow;
+use std::sync::atomic::{AtomicUsize,Ordering,fence};
+
+/// Inner allocation shared by every `ZeroDropArc<T>` clone.
+struct ZeroDropArcInner<T> where T: Copy {
+    strong: AtomicUsize,
+    data: T,
+}
+
+/// Reference-counted zeroing drop wrapper type for `Copy` data.
+///
+/// `ZeroDropArc<T>` lets a secret be shared across tasks or threads
+/// without copying it into a fresh `ZeroDrop<T>` for every holder,
+/// each of which would be its own zeroing liability.  Cloning only
+/// bumps a strong count; the underlying allocation is zeroed exactly
+/// once, when the last clone drops, following the same acquire/release
+/// discipline `std::sync::Arc` uses around its strong count so the
+/// zeroing-on-last-drop invariant holds under concurrent drops.
+#[derive(Debug)]
+pub struct ZeroDropArc<T>(*mut ZeroDropArcInner<T>) where T: Copy;
+
+unsafe impl<T> Send for ZeroDropArc<T> where T: Copy+Sync+Send {}
+unsafe impl<T> Sync for ZeroDropArc<T> where T: Copy+Sync+Send {}
+
+impl<T> ZeroDropArc<T> where T: Copy {
+    /// Allocate a fresh `ZeroDropArc<T>` with strong count 1.
+    pub fn new(t: T) -> ZeroDropArc<T> {
+        let inner = Box::new(ZeroDropArcInner { strong: AtomicUsize::new(1), data: t });
+        ZeroDropArc(Box::into_raw(inner))
+    }
+
+    fn inner(&self) -> &ZeroDropArcInner<T> {
+        unsafe { &*self.0 }
+    }
+
+    /// Number of `ZeroDropArc<T>` handles currently sharing this
+    /// allocation.
+    pub fn strong_count(this: &ZeroDropArc<T>) -> usize {
+        this.inner().strong.load(Ordering::SeqCst)
+    }
+
+    /// Reclaim the inner `T` if `this` is the sole remaining handle,
+    /// otherwise hand `this` back unchanged.
+    ///
+    /// Succeeds only at strong count 1, exactly like
+    /// `std::sync::Arc::try_unwrap`.
+    pub fn try_unwrap(this: ZeroDropArc<T>) -> Result<T, ZeroDropArc<T>> {
+        if this.inner().strong.compare_and_swap(1, 0, Ordering::Acquire) != 1 {
+            return Err(this);
+        }
+        fence(Ordering::Acquire);
+        let ptr = this.0;
+        ::std::mem::forget(this);
+        let mut inner = unsafe { Box::from_raw(ptr) };
+        let data = inner.data;
+        // Zero the allocation's copy before `inner` deallocates it,
+        // exactly as `Drop` does, so the secret does not linger
+        // unzeroed in freed heap memory.
+        unsafe { ::std::intrinsics::volatile_set_memory::<T>(&mut inner.data,0,1); }
+        Ok(data)
+    }
+
+    /// Alias for `try_unwrap` matching the vocabulary used elsewhere
+    /// in this crate (`ZeroDropCow::into_owned`).
+    pub fn into_inner(this: ZeroDropArc<T>) -> Result<T, ZeroDropArc<T>> {
+        ZeroDropArc::try_unwrap(this)
+    }
+}
+
+/// Bump the strong count; the allocation is shared, not copied.
+impl<T> Clone for ZeroDropArc<T> where T: Copy {
+    fn clone(&self) -> ZeroDropArc<T> {
+        // `Relaxed` is sufficient here because the count itself does
+        // not guard access to any other memory until it is dropped
+        // to zero, exactly as in `std::sync::Arc::clone`.
+        self.inner().strong.fetch_add(1, Ordering::Relaxed);
+        ZeroDropArc(self.0)
+    }
+}
+
+/// Decrement the strong count, zeroing and freeing the allocation
+/// when it reaches zero.
+impl<T> Drop for ZeroDropArc<T> where T: Copy {
+    #[inline(never)]
+    fn drop(&mut self) {
+        if self.inner().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        // Synchronize with every other `Release` decrement so the
+        // final zeroing happens-after all prior reads of `data`.
+        fence(Ordering::Acquire);
+        unsafe {
+            let data = &mut (*self.0).data;
+            ::std::intrinsics::volatile_set_memory::<T>(data,0,1);
+            drop(Box::from_raw(self.0));
+        }
+    }
+}
+
+/// Delegate `Deref` to the shared allocation.
+impl<T> Deref for ZeroDropArc<T> where T: Copy {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner().data
+    }
+}
+
+/// Delegate `AsRef<_>` to the shared allocation.
+impl<T,U> AsRef<U> for ZeroDropArc<T> where T: Copy+AsRef<U> {
+    fn as_ref(&self) -> &U {
+        self.inner().data.as_ref()
+    }
+}
+
+/// Delegate `Borrow<_>` to the shared allocation.
+impl<T> Borrow<T> for ZeroDropArc<T> where T: Copy {
+    fn borrow(&self) -> &T {
+        &self.inner().data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clone_shares_and_bumps_count() {
+        let a = ZeroDropArc::new([3u8; 32]);
+        let b = a.clone();
+        assert_eq!(ZeroDropArc::strong_count(&a), 2);
+        assert_eq!(*a, *b);
+    }
+
+    #[test]
+    fn zeroing_drops_on_last_clone() {
+        let p: *const [u8; 32];
+        let a = ZeroDropArc::new([3u8; 32]);
+        let b = a.clone();
+        p = &*a;
+        ::std::mem::drop(a);
+        unsafe { assert_eq!(*p, [3u8; 32]); } // still alive via `b`
+        ::std::mem::drop(b);
+        unsafe { assert_eq!(*p, [0u8; 32]); }
+    }
+
+    #[test]
+    fn try_unwrap_requires_sole_ownership() {
+        let a = ZeroDropArc::new([3u8; 32]);
+        let b = a.clone();
+        let a = ZeroDropArc::try_unwrap(a).unwrap_err();
+        ::std::mem::drop(b);
+        assert_eq!(ZeroDropArc::try_unwrap(a).unwrap(), [3u8; 32]);
+    }
+}
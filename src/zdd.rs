@@ -7,6 +7,8 @@ use std::ops::{Deref,DerefMut};
 use std::convert::{AsRef,AsMut};
 use std::borrow::{Borrow,BorrowMut};
 
+use super::ConstantTimeEq;
+
 /// Zeroing drop wrapper type for `Drop` types.
 ///
 /// `ZeroDropDrop<T>` wraps a `Box<T>` where `T: Drop+Default`.
@@ -118,26 +120,14 @@ impl<T> BorrowMut<T> for ZeroDropDrop<T> where T: Drop+Default {
 }
 
 
-/*
-trait ConstantTimeEq {
-    fn constant_time_eq(a: &Self, b: &Self) -> bool;
-}
-
-impl<T> ConstantTimeEq for [T] where T: ConstantTimeEq {
-    fn constant_time_eq(x: &Self, y: &Self) -> bool {
-        ;
-    }
-}
-
 /// We implement `PartialEq` to be a constant time comparison, so that
-/// noone may define it otherwise.  
-impl<T> PartialEq for ZeroDrop<T> where T: ConstantTimeEq {
-    fn eq(&self, other: &ZeroDrop<T>) -> bool {
-        ::consistenttime::ct_u8_slice_eq(&self.0, &other.0)
+/// noone may define it otherwise.
+impl<T> PartialEq for ZeroDropDrop<T> where T: Drop+Default+ConstantTimeEq {
+    fn eq(&self, other: &ZeroDropDrop<T>) -> bool {
+        self.0.deref().ct_eq(other.0.deref())
     }
 }
-impl<T> Eq for Secret<T>  where T: Copy { }
-*/
+impl<T> Eq for ZeroDropDrop<T> where T: Drop+Default+ConstantTimeEq { }
 
 
 /*
@@ -165,4 +155,39 @@ mod tests {
 }
 */
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Droppable([u8; 4]);
+
+    impl Drop for Droppable {
+        fn drop(&mut self) {}
+    }
+
+    impl ConstantTimeEq for Droppable {
+        fn ct_eq(&self, other: &Self) -> bool {
+            self.0.ct_eq(&other.0)
+        }
+    }
+
+    #[test]
+    fn equal_values_are_equal() {
+        let mut a = ZeroDropDrop::new_default();
+        *a = Droppable([1,2,3,4]);
+        let mut b = ZeroDropDrop::new_default();
+        *b = Droppable([1,2,3,4]);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn differing_values_are_not_equal() {
+        let mut a = ZeroDropDrop::new_default();
+        *a = Droppable([1,2,3,4]);
+        let mut b = ZeroDropDrop::new_default();
+        *b = Droppable([5,6,7,8]);
+        assert!(a != b);
+    }
+}
 
@@ -0,0 +1,97 @@
+// Copyright 2016 Jeffrey Burdges
+
+//! Zeroing scope guard for borrowed secrets.
+
+use std::ops::{Deref,DerefMut};
+
+/// RAII guard that zeros a borrowed secret when it goes out of scope.
+///
+/// Every other type in this crate boxes its secret because, as
+/// `ZeroDrop` warns, LLVM moves data on the stack willy nilly.  But
+/// sometimes a caller genuinely must keep a secret in memory it
+/// already owns and cannot box, such as an FFI-filled `[u8; N]` on
+/// the stack.  `ZeroGuard<'a, T>` wraps `&'a mut T` and runs the same
+/// `volatile_set_memory` zeroing over it on drop, giving that
+/// caller-owned memory the same zeroing guarantee `ZeroDrop` gives a
+/// `Box`.
+///
+/// As with `ZeroDropArc` and `ZeroDropCow`, `T: Copy` is required:
+/// zeroing an arbitrary `&mut T` in place is unsound for types that
+/// own a heap allocation (`Vec<u8>`, `Box<_>`, `String`, etc.), since
+/// stomping their pointer/len/cap fields with zero bytes leaves their
+/// real owner's eventual drop glue running over an invalid value.
+///
+/// ```rust
+/// let mut buf = [3u8; 32];
+/// {
+///     let _guard = zerodrop::ZeroGuard::new(&mut buf);
+/// }
+/// assert_eq!(buf, [0u8; 32]);
+/// ```
+///
+/// Call `defuse` to cancel the zeroing, e.g. because the buffer was
+/// deliberately handed off to someone else who now owns zeroing it.
+pub struct ZeroGuard<'a, T: 'a+Copy>(Option<&'a mut T>);
+
+impl<'a, T> ZeroGuard<'a, T> where T: Copy {
+    /// Guard `t`, zeroing it when the guard drops.
+    pub fn new(t: &'a mut T) -> ZeroGuard<'a, T> {
+        ZeroGuard(Some(t))
+    }
+
+    /// Cancel the zeroing and hand the borrow back, consuming the
+    /// guard without touching the memory it was protecting.
+    pub fn defuse(mut self) -> &'a mut T {
+        self.0.take().expect("ZeroGuard always holds its borrow until dropped or defused")
+    }
+}
+
+/// Zero the guarded value, unless `defuse` already took it.
+impl<'a, T> Drop for ZeroGuard<'a, T> where T: Copy {
+    #[inline(never)]
+    fn drop(&mut self) {
+        if let Some(ref mut t) = self.0 {
+            unsafe { ::std::intrinsics::volatile_set_memory::<T>(*t,0,1) }
+        }
+    }
+}
+
+/// Delegate `Deref` to the borrowed value.
+impl<'a, T> Deref for ZeroGuard<'a, T> where T: Copy {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0.as_ref().expect("ZeroGuard always holds its borrow until dropped or defused")
+    }
+}
+
+/// Delegate `DerefMut` to the borrowed value.
+impl<'a, T> DerefMut for ZeroGuard<'a, T> where T: Copy {
+    fn deref_mut(&mut self) -> &mut T {
+        self.0.as_mut().expect("ZeroGuard always holds its borrow until dropped or defused")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zeroing_drops() {
+        let mut buf = [3u8; 32];
+        {
+            let _guard = ZeroGuard::new(&mut buf);
+        }
+        assert_eq!(buf, [0u8; 32]);
+    }
+
+    #[test]
+    fn defuse_cancels_zeroing() {
+        let mut buf = [3u8; 32];
+        {
+            let guard = ZeroGuard::new(&mut buf);
+            guard.defuse();
+        }
+        assert_eq!(buf, [3u8; 32]);
+    }
+}